@@ -18,10 +18,12 @@
 
 #![warn(clippy::all, missing_docs, nonstandard_style, future_incompatible)]
 
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
 use threadpool::ThreadPool;
 use walkdir::WalkDir;
 
@@ -30,6 +32,43 @@ pub struct Warmer {
     paths: Vec<PathBuf>,
     num_threads: usize,
     follow_links: bool,
+    dedup_links: bool,
+}
+
+/// An identifier used to tell whether two directory entries point to the same physical file
+///
+/// On Unix it's the `(dev, ino)` pair, so hard links and directories reached twice (e.g. via
+/// `follow_links`) resolve to the same id. Elsewhere we fall back to the canonicalized path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct NodeId {
+    #[cfg(unix)]
+    dev: u64,
+    #[cfg(unix)]
+    ino: u64,
+    #[cfg(not(unix))]
+    path: PathBuf,
+}
+
+impl NodeId {
+    /// Derives the id of a directory entry, if its metadata (on Unix) or its canonicalized
+    /// path (elsewhere) can be read
+    fn new(entry: &walkdir::DirEntry) -> Option<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let meta = entry.metadata().ok()?;
+            Some(Self {
+                dev: meta.dev(),
+                ino: meta.ino(),
+            })
+        }
+        #[cfg(not(unix))]
+        {
+            Some(Self {
+                path: entry.path().canonicalize().ok()?,
+            })
+        }
+    }
 }
 
 /// Iterator over the size estimation / file reading bytes
@@ -45,9 +84,15 @@ impl Warmer {
             paths,
             num_threads,
             follow_links,
+            dedup_links: true,
         }
     }
 
+    /// Enables or disables skipping hard links / repeated inodes, on by default
+    pub fn set_dedup_links(&mut self, dedup_links: bool) {
+        self.dedup_links = dedup_links;
+    }
+
     /// Estimates total size to read, returns the total number of bytes
     pub fn estimate(&self) -> u64 {
         self.iter_estimate().sum()
@@ -64,12 +109,18 @@ impl Warmer {
         let paths = self.paths.clone();
         let num_threads = self.num_threads;
         let follow_links = self.follow_links;
+        let dedup_links = self.dedup_links;
         std::thread::spawn(move || {
             let pool = ThreadPool::new(num_threads);
+            let seen = Arc::new(Mutex::new(HashSet::new()));
             for path in paths {
                 for entry in walker(path, follow_links) {
                     let tx = tx.clone();
+                    let seen = Arc::clone(&seen);
                     pool.execute(move || {
+                        if dedup_links && !is_unseen(&entry, &seen) {
+                            return;
+                        }
                         if let Ok(size) = entry.metadata().map(|m| m.len()) {
                             tx.send(size).ok();
                         }
@@ -86,12 +137,18 @@ impl Warmer {
         let paths = self.paths.clone();
         let num_threads = self.num_threads;
         let follow_links = self.follow_links;
+        let dedup_links = self.dedup_links;
         std::thread::spawn(move || {
             let pool = ThreadPool::new(num_threads);
+            let seen = Arc::new(Mutex::new(HashSet::new()));
             for path in paths {
                 for entry in walker(path, follow_links) {
                     let tx = tx.clone();
+                    let seen = Arc::clone(&seen);
                     pool.execute(move || {
+                        if dedup_links && !is_unseen(&entry, &seen) {
+                            return;
+                        }
                         if let Ok(mut file) = File::open(entry.path()) {
                             let mut buffer = [0; 1024];
                             loop {
@@ -110,6 +167,17 @@ impl Warmer {
     }
 }
 
+/// Returns `true` the first time a given entry's `NodeId` is seen by this pass
+///
+/// Entries whose id can't be determined are always treated as unseen, so they're never
+/// silently dropped.
+fn is_unseen(entry: &walkdir::DirEntry, seen: &Mutex<HashSet<NodeId>>) -> bool {
+    match NodeId::new(entry) {
+        Some(id) => seen.lock().unwrap().insert(id),
+        None => true,
+    }
+}
+
 /// Initializes and returns a `walkdir::WalkDir` instance
 fn walker(path: impl AsRef<Path>, follow_links: bool) -> impl Iterator<Item = walkdir::DirEntry> {
     let mut w = WalkDir::new(path);